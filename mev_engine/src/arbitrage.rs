@@ -0,0 +1,124 @@
+// mev_engine/src/arbitrage.rs
+//
+// Detects cyclic (atomic) arbitrage: a transaction (or cluster of transactions
+// from the same sender) that chains swaps `token_a -> token_b -> ... -> token_a`
+// at a combined exchange rate greater than 1 after fees.
+
+use std::collections::{HashMap, HashSet};
+
+/// One swap edge in the token graph: `amount_in` of `token_in` becomes
+/// `rate * amount_in` of `token_out` (fees already folded into `rate`).
+#[derive(Debug, Clone)]
+pub struct SwapEdge {
+    pub token_in: String,
+    pub token_out: String,
+    pub rate: f64,
+    pub tx_hash: String,
+    pub sender: String,
+}
+
+/// A detected profitable cycle through the token graph.
+#[derive(Debug, Clone)]
+pub struct ArbitrageCycle {
+    /// Token addresses visited, in order, starting and ending at the same token.
+    pub path: Vec<String>,
+    /// Transaction hashes of the swaps that make up the cycle, in path order.
+    pub tx_hashes: Vec<String>,
+    /// `sender` shared by every edge in the cycle, if there is one (a single
+    /// transaction can only be flagged as *atomic* arbitrage when this is `Some`).
+    pub sender: Option<String>,
+    /// Product of the cycle's exchange rates; > 1.0 means the cycle is profitable.
+    pub rate_product: f64,
+}
+
+/// Finds a negative cycle in the graph formed by `edges`, using Bellman-Ford on
+/// `-ln(rate)` edge weights. A cycle is profitable exactly when the sum of
+/// `-ln(rate)` around it is negative, i.e. the product of rates exceeds 1.
+///
+/// Standard multi-source Bellman-Ford: seed every node at distance 0 (so the
+/// search isn't biased toward one starting token), relax all edges `|V| - 1`
+/// times, then do one more pass — any edge that still relaxes lies on or
+/// downstream of a negative cycle, and following predecessors far enough back
+/// from there is guaranteed to land inside it.
+pub fn find_negative_cycle(edges: &[SwapEdge]) -> Option<ArbitrageCycle> {
+    let mut nodes: Vec<String> = Vec::new();
+    let mut seen_nodes: HashSet<&str> = HashSet::new();
+    for edge in edges {
+        if seen_nodes.insert(edge.token_in.as_str()) {
+            nodes.push(edge.token_in.clone());
+        }
+        if seen_nodes.insert(edge.token_out.as_str()) {
+            nodes.push(edge.token_out.clone());
+        }
+    }
+    if nodes.len() < 2 {
+        return None;
+    }
+
+    let mut dist: HashMap<String, f64> = nodes.iter().map(|n| (n.clone(), 0.0)).collect();
+    let mut pred: HashMap<String, usize> = HashMap::new();
+
+    let mut last_relaxed: Option<usize> = None;
+    for _ in 0..nodes.len() {
+        last_relaxed = None;
+        for (i, edge) in edges.iter().enumerate() {
+            let weight = -edge.rate.ln();
+            let candidate = dist[&edge.token_in] + weight;
+            if candidate < dist[&edge.token_out] - f64::EPSILON {
+                dist.insert(edge.token_out.clone(), candidate);
+                pred.insert(edge.token_out.clone(), i);
+                last_relaxed = Some(i);
+            }
+        }
+    }
+
+    // No edge relaxed on the final pass: no negative cycle exists.
+    let relaxed_edge = edges.get(last_relaxed?)?;
+    let mut cycle_node = relaxed_edge.token_out.clone();
+
+    // Walk back |V| times to guarantee landing on a node that is actually
+    // inside the cycle, not just reachable from it.
+    for _ in 0..nodes.len() {
+        let edge_idx = *pred.get(&cycle_node)?;
+        cycle_node = edges[edge_idx].token_in.clone();
+    }
+
+    // Now walk the predecessor chain from `cycle_node` back to itself to
+    // recover the cycle's edges, in visitation order.
+    let start = cycle_node.clone();
+    let mut cycle_edges: Vec<&SwapEdge> = Vec::new();
+    let mut current = start.clone();
+    loop {
+        let edge_idx = *pred.get(&current)?;
+        let edge = &edges[edge_idx];
+        cycle_edges.push(edge);
+        current = edge.token_in.clone();
+        if current == start {
+            break;
+        }
+    }
+    cycle_edges.reverse();
+
+    let mut path: Vec<String> = vec![start.clone()];
+    for edge in &cycle_edges {
+        path.push(edge.token_out.clone());
+    }
+
+    let rate_product: f64 = cycle_edges.iter().map(|e| e.rate).product();
+
+    let sender = {
+        let first_sender = &cycle_edges[0].sender;
+        if cycle_edges.iter().all(|e| &e.sender == first_sender) {
+            Some(first_sender.clone())
+        } else {
+            None
+        }
+    };
+
+    Some(ArbitrageCycle {
+        path,
+        tx_hashes: cycle_edges.iter().map(|e| e.tx_hash.clone()).collect(),
+        sender,
+        rate_product,
+    })
+}
@@ -9,6 +9,28 @@ use wasm_bindgen::prelude::*;
 // to/from JSON strings, which is how data will pass between Node.js and Rust.
 use serde::{Serialize, Deserialize};
 
+// Constant-product pool simulation, used to check whether a candidate
+// sandwich is actually profitable rather than just well-timed.
+mod simulate;
+use simulate::{find_optimal_frontrun, simulate_sandwich, swap_output};
+
+// Cyclic (atomic) arbitrage detection: chains swaps into a token graph and
+// looks for a profitable cycle, independent of the sandwich detectors above.
+mod arbitrage;
+use arbitrage::{find_negative_cycle, SwapEdge};
+
+// "Salmonella" guard: flags tokens (transfer-tax, honeypot/blacklist) whose
+// real behavior diverges from the constant-product simulation, so a sandwich
+// that only looks profitable on paper isn't reported as a real opportunity.
+mod token_safety;
+use token_safety::{is_safe, TokenSafetyCheckParams, DEFAULT_TOLERANCE_BPS};
+
+// Decodes ERC-20 `Transfer` events out of a transaction's logs, so attacker
+// and victim transactions can be matched by the token flows they actually
+// produced instead of by a shared `to` (router) address.
+mod transfers;
+use transfers::parse_transfers;
+
 // Define a simplified `Transaction` struct.
 // This struct represents the data we expect for each transaction from Node.js.
 // In a real-world scenario, this would be much more detailed, including
@@ -26,6 +48,7 @@ pub struct Transaction {
     pub input: String,         // Transaction input data (calldata), contains function calls
     pub timestamp: u64,        // Timestamp when the transaction was seen/mined (in seconds since epoch)
     pub block_number: u64,     // Block number the transaction is in (0 if pending)
+    pub tx_index: u64,         // Position of the transaction within its block; same-block MEV is ordered by this, not by timestamp
     pub sender: String,        // For sandwich attack detection: the address initiating the transaction (often same as `from`)
     pub slippage_tolerance: Option<f64>, // Example: How much price movement the victim tolerates (e.g., 0.01 for 1%)
     pub is_uniswap_swap: bool, // Simplified flag: true if this is a Uniswap-like swap
@@ -33,14 +56,30 @@ pub struct Transaction {
     pub token_out: Option<String>,     // Optional: Address of the token being swapped out
     pub amount_in: Option<String>,     // Optional: Amount of token being swapped in
     pub amount_out_min: Option<String>,// Optional: Minimum amount of token expected out (for slippage calculation)
+    pub reserve_in: Option<String>,    // Optional: Pool reserve of `token_in` at the time of this tx, for profitability simulation
+    pub reserve_out: Option<String>,   // Optional: Pool reserve of `token_out` at the time of this tx, for profitability simulation
+    pub logs: Option<Vec<Log>>,        // Optional: Event logs emitted by this tx, used to decode ERC-20 Transfer events
+    pub token_safety: Option<TokenSafetyCheckParams>, // Optional: buy/sell round-trip simulation, for the salmonella guard
+}
+
+// A single event log entry as returned by an Ethereum JSON-RPC provider
+// (e.g. `eth_getTransactionReceipt`'s `logs` field via ethers.js/web3.js).
+// We only need enough of the log to decode `Transfer` events from it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Log {
+    pub address: String,       // Contract (token) address that emitted the log
+    pub topics: Vec<String>,   // Indexed event arguments; topics[0] is the event signature hash
+    pub data: String,          // ABI-encoded non-indexed event arguments
 }
 
-// Enum to represent the direction of a transaction relative to a victim transaction.
-// Used to find frontrun (before) or backrun (after) transactions.
-#[derive(Debug, PartialEq, Eq)]
-pub enum Direction {
-    Before,
-    After,
+// Parses a decimal amount string into a `u128`, treating anything missing or
+// unparseable as zero. Amounts travel as strings across the WASM boundary to
+// avoid precision loss, so this converts a `Transaction`'s amount fields back.
+fn parse_amount(amount: &Option<String>) -> u128 {
+    amount
+        .as_ref()
+        .and_then(|s| s.parse::<u128>().ok())
+        .unwrap_or(0)
 }
 
 // Helper function: Simulates finding a Uniswap swap transaction within a list of transactions.
@@ -54,40 +93,149 @@ fn find_uniswap_swap(transactions: &[Transaction]) -> Option<Transaction> {
         .cloned() // `.cloned()` creates a copy of the found transaction.
 }
 
-// Helper function: Simulates finding a matching transaction (potential frontrun or backrun).
-// This is a highly simplified version for demonstration. A real implementation would:
-// - Analyze gas prices: Frontruns usually have significantly higher gas prices.
-// - Analyze transaction `to` addresses and `input` data: Ensure they interact with the same DEX pool/router.
-// - Check for token/amount matches: Ensure the transactions are related to the same asset swap.
-// - Consider transaction ordering within a block (e.g., transaction index).
-fn find_matching_tx(
-    transactions: &[Transaction], // List of transactions to search within
-    victim: &Transaction,         // The victim transaction we're looking around
-    direction: Direction,         // Whether to look `Before` or `After` the victim
-) -> Option<Transaction> {
-    transactions.iter()
+// Helper function: finds the (frontrun, backrun) pair around a victim transaction
+// by matching the token transfers they produced, rather than by a shared `to`
+// (router) address. A shared `to` misses attacks routed through different
+// contracts and false-positives on unrelated swaps to the same router, so
+// instead we require:
+// 1. The frontrun's `sender` is the recipient of the victim's `token_out` (it
+//    buys the same token the victim is about to buy, pushing the price up).
+// 2. The victim swaps that same directional pair (`token_in` -> `token_out`).
+// 3. The backrun sells `token_out` back for `token_in`, with the same `sender`
+//    as the frontrun, and that sender's net position in `token_out` is
+//    approximately zero (bought then fully sold).
+fn find_sandwich_by_flow(
+    transactions: &[Transaction],
+    victim: &Transaction,
+) -> Option<(Transaction, Transaction)> {
+    let victim_token_in = victim.token_in.as_ref()?;
+    let victim_token_out = victim.token_out.as_ref()?;
+
+    // Frontrun candidates: transactions ordered strictly before the victim by
+    // `(block_number, tx_index)` — not wall-clock timestamp, since same-block
+    // MEV transactions can all share one timestamp but are only ever ordered
+    // by their position in the block — whose sender received the victim's
+    // `token_out`. Pick the one closest to the victim in block order.
+    let victim_pos = (victim.block_number, victim.tx_index);
+    let frontrun = transactions.iter()
+        .filter(|tx| tx.hash != victim.hash && (tx.block_number, tx.tx_index) < victim_pos)
+        .filter_map(|tx| {
+            parse_transfers(tx).into_iter()
+                .find(|t| &t.token == victim_token_out && t.to == tx.sender)
+                .map(|t| (tx.clone(), t.amount))
+        })
+        .max_by_key(|(tx, _)| (tx.block_number, tx.tx_index));
+
+    let (fr_tx, bought_amount) = frontrun?;
+
+    // Backrun candidates: transactions ordered strictly after the victim by
+    // `(block_number, tx_index)`, from the same sender as the frontrun, that
+    // sell `token_out` back for `token_in`.
+    let backrun = transactions.iter()
         .filter(|tx| {
-            // Basic check: `to` address is the same (e.g., same DEX router/pair)
-            // and it's not the victim transaction itself.
-            tx.to == victim.to && tx.hash != victim.hash
-            // In a real scenario, you'd add more complex filtering here:
-            // - Check if the transaction is a swap of the same token pair.
-            // - Check if the sender is an EOA or a contract known for bot activity.
-            // - Compare gas prices (e.g., `tx.gas_price` vs `victim.gas_price`).
+            tx.hash != victim.hash && tx.hash != fr_tx.hash
+                && tx.sender == fr_tx.sender
+                && (tx.block_number, tx.tx_index) > victim_pos
         })
-        .min_by_key(|tx| {
-            // Sort by timestamp to find the closest transaction in the specified direction.
-            if direction == Direction::Before {
-                // For "Before", we want the smallest positive difference (victim_timestamp - tx_timestamp)
-                // This means `tx.timestamp` is just before `victim.timestamp`.
-                victim.timestamp - tx.timestamp
+        .filter_map(|tx| {
+            let xfers = parse_transfers(tx);
+            let sold_amount: u128 = xfers.iter()
+                .filter(|t| &t.token == victim_token_out && t.from == tx.sender)
+                .map(|t| t.amount)
+                .sum();
+            let receives_in = xfers.iter()
+                .any(|t| &t.token == victim_token_in && t.to == tx.sender);
+
+            if sold_amount > 0 && receives_in {
+                Some((tx.clone(), sold_amount))
             } else {
-                // For "After", we want the smallest positive difference (tx_timestamp - victim_timestamp)
-                // This means `tx.timestamp` is just after `victim.timestamp`.
-                tx.timestamp - victim.timestamp
+                None
             }
         })
-        .cloned() // Return a clone of the found transaction.
+        .min_by_key(|(tx, _)| (tx.block_number, tx.tx_index));
+
+    let (br_tx, sold_amount) = backrun?;
+
+    // The attacker's net position in `token_out` should be ~zero: it bought
+    // `bought_amount` in the frontrun and sold `sold_amount` in the backrun,
+    // within a small tolerance for fees/slippage rather than an exact match.
+    if bought_amount == 0 {
+        return None;
+    }
+    let diff = bought_amount.abs_diff(sold_amount);
+    let within_tolerance = diff.saturating_mul(100) <= bought_amount.saturating_mul(2);
+
+    if within_tolerance {
+        Some((fr_tx, br_tx))
+    } else {
+        None
+    }
+}
+
+// Finds a (frontrun, victim, backrun) triple that passes the timing/sender/slippage
+// conditions shared by every sandwich check, regardless of whether the caller then
+// wants a plain boolean or a detailed profitability estimate.
+fn find_sandwich_candidate(transactions: &[Transaction]) -> Option<(Transaction, Transaction, Transaction)> {
+    // Ensure there are enough transactions in the cluster to even attempt a sandwich detection.
+    // A sandwich attack requires at least 3 transactions: frontrun, victim, backrun.
+    if transactions.len() < 3 {
+        return None;
+    }
+
+    // Attempt to find the victim (Uniswap swap) transaction within the cluster.
+    let victim = find_uniswap_swap(transactions)?;
+
+    // Find the frontrun/backrun pair whose token flows match this victim.
+    let (fr, br) = find_sandwich_by_flow(transactions, &victim)?;
+
+    // Condition 1: Frontrun and backrun are from the same sender (this is the attacker bot).
+    // Already implied by `find_sandwich_by_flow`, but kept explicit here since
+    // the rest of this function still reasons in terms of the four conditions.
+    let same_sender = fr.sender == br.sender;
+
+    // Condition 2: the triple must be sequenced the way sandwiches actually are
+    // on-chain — by block position, not wall-clock time (same-block transactions
+    // can all share one timestamp). `find_sandwich_by_flow` already guarantees
+    // `fr`'s position <= victim's <= `br`'s, so the only thing left to check is
+    // how far apart they are:
+    // - If all three share a block, it's only a true atomic sandwich when the
+    //   indices are contiguous (frontrun, then victim, then backrun back to back).
+    // - If the backrun spills into the very next block, we relax the index
+    //   check (some bots split the sandwich across a block boundary when they
+    //   miss the victim's block), but still require it be exactly one block over.
+    // - Anything wider than that is too loose to be the same attack.
+    let same_block_contiguous = fr.block_number == victim.block_number
+        && victim.block_number == br.block_number
+        && victim.tx_index == fr.tx_index + 1
+        && br.tx_index == victim.tx_index + 1;
+    let block_span = br.block_number.saturating_sub(fr.block_number);
+    let block_ordering_ok = if block_span == 0 {
+        same_block_contiguous
+    } else {
+        block_span == 1
+    };
+
+    // Condition 3 (from problem statement): Victim has high slippage tolerance.
+    // We check if `victim.slippage_tolerance` exists (`is_some()`) and if its value
+    // is greater than a threshold (e.g., 0.05 for 5%).
+    let high_slippage_victim = victim.slippage_tolerance.map_or(false, |s| s > 0.05);
+
+    if same_sender && block_ordering_ok && high_slippage_victim {
+        Some((fr, victim, br))
+    } else {
+        None
+    }
+}
+
+// Checks the salmonella guard for a victim transaction: if the caller supplied
+// a buy/sell round-trip simulation for the victim's token, the token must pass
+// it. Absent that data we can't evaluate safety, so we don't veto detection
+// (mirrors how the profitability check falls back when reserves are absent).
+fn victim_token_is_safe(victim: &Transaction) -> bool {
+    match &victim.token_safety {
+        Some(params) => is_safe(params, params.tolerance_bps.unwrap_or(DEFAULT_TOLERANCE_BPS)),
+        None => true,
+    }
 }
 
 // Main MEV detection logic, exposed to JavaScript via WASM.
@@ -106,50 +254,241 @@ pub fn detect_mev_sandwich(transactions_json: &str) -> bool {
         }
     };
 
-    // Ensure there are enough transactions in the cluster to even attempt a sandwich detection.
-    // A sandwich attack requires at least 3 transactions: frontrun, victim, backrun.
-    if transactions.len() < 3 {
+    let (fr, victim, _br) = match find_sandwich_candidate(&transactions) {
+        Some(triple) => triple,
+        None => return false,
+    };
+
+    // Salmonella guard: a token whose real transfer behavior diverges from the
+    // constant-product simulation (transfer tax, blacklist/honeypot) would make
+    // a simulated-profitable sandwich fake in practice, so don't report it.
+    if !victim_token_is_safe(&victim) {
         return false;
     }
 
-    // Attempt to find the victim (Uniswap swap) transaction within the cluster.
-    let victim = match find_uniswap_swap(&transactions) {
-        Some(v) => v,       // If found, assign it to `v`.
-        None => return false, // No Uniswap swap found, so no sandwich attack of this type.
+    // Condition 4: the frontrun/backrun pair must actually be profitable against
+    // the pool's reserves, not just well-timed. If the caller supplied reserves
+    // (and the victim's swap amounts), simulate the constant-product pool rather
+    // than trusting coincidental timing; if reserves are absent, fall back to the
+    // timing-only heuristic so callers that don't have pool state still work.
+    match (&victim.reserve_in, &victim.reserve_out) {
+        (Some(_), Some(_)) => {
+            let reserve_in = parse_amount(&victim.reserve_in);
+            let reserve_out = parse_amount(&victim.reserve_out);
+            let frontrun_in = parse_amount(&fr.amount_in);
+            let victim_in = parse_amount(&victim.amount_in);
+            let victim_out_min = parse_amount(&victim.amount_out_min);
+
+            let result = simulate_sandwich(
+                frontrun_in,
+                victim_in,
+                victim_out_min,
+                reserve_in,
+                reserve_out,
+            );
+
+            result.victim_succeeds && result.profit > 0
+        }
+        // No pool state supplied: can't simulate, so don't let this condition
+        // veto detections that the rest of the codebase already relies on.
+        _ => true,
+    }
+}
+
+// Detailed result of a sandwich detection pass, serialized to JSON for the
+// Node.js layer. Unlike `detect_mev_sandwich`'s plain `bool`, this carries
+// enough information to rank opportunities by estimated extracted value.
+#[derive(Debug, Clone, Serialize)]
+pub struct SandwichDetectionResult {
+    pub detected: bool,
+    pub attacker: Option<String>,
+    pub victim_hash: Option<String>,
+    pub estimated_profit_wei: Option<String>,
+    pub optimal_frontrun_in: Option<String>,
+}
+
+impl SandwichDetectionResult {
+    fn not_detected() -> Self {
+        SandwichDetectionResult {
+            detected: false,
+            attacker: None,
+            victim_hash: None,
+            estimated_profit_wei: None,
+            optimal_frontrun_in: None,
+        }
+    }
+}
+
+// Same detection pipeline as `detect_mev_sandwich`, but instead of a plain
+// `bool` this estimates how much MEV was extracted: it ternary-searches for the
+// frontrun input that maximizes attacker profit against the pool's reserves,
+// so the Node layer can rank opportunities rather than just flag them.
+#[wasm_bindgen]
+pub fn detect_mev_sandwich_detailed(transactions_json: &str) -> String {
+    let not_detected = SandwichDetectionResult::not_detected();
+
+    let transactions: Vec<Transaction> = match serde_json::from_str(transactions_json) {
+        Ok(txs) => txs,
+        Err(e) => {
+            eprintln!("Error deserializing transactions: {:?}", e);
+            return serde_json::to_string(&not_detected).unwrap_or_default();
+        }
     };
 
-    // Find potential frontrun and backrun transactions relative to the victim.
-    let frontrun = find_matching_tx(&transactions, &victim, Direction::Before);
-    let backrun = find_matching_tx(&transactions, &victim, Direction::After);
-
-    // Check for the conditions of a sandwich attack as per the problem statement.
-    // We use `if let (Some(fr), Some(br)) = (frontrun, backrun)` to check if both
-    // frontrun AND backrun transactions were successfully found.
-    if let (Some(fr), Some(br)) = (frontrun, backrun) {
-        // Condition 1: Frontrun and backrun are from the same sender (this is the attacker bot).
-        let same_sender = fr.sender == br.sender;
-
-        // Condition 2: Backrun timestamp is within 120 seconds (approx. 2 Ethereum blocks)
-        // of the frontrun timestamp. This is a simplified time analysis.
-        // In reality, you'd look at block numbers and potentially transaction indices within blocks
-        // to confirm they are in the same or very close blocks.
-        let time_within_limit = if br.timestamp > fr.timestamp {
-            br.timestamp - fr.timestamp < 120
-        } else {
-            // This case should ideally not happen if timestamps are accurate and ordered,
-            // but added for robustness in case of out-of-order data.
-            fr.timestamp - br.timestamp < 120
-        };
-
-        // Condition 3 (from problem statement): Victim has high slippage tolerance.
-        // We check if `victim.slippage_tolerance` exists (`is_some()`) and if its value
-        // is greater than a threshold (e.g., 0.05 for 5%).
-        let high_slippage_victim = victim.slippage_tolerance.map_or(false, |s| s > 0.05);
-
-        // Combine all conditions: All must be true for a detected sandwich attack.
-        same_sender && time_within_limit && high_slippage_victim
-    } else {
-        // If either frontrun or backrun was not found, it's not a complete sandwich attack.
-        false
+    let (fr, victim, _br) = match find_sandwich_candidate(&transactions) {
+        Some(triple) => triple,
+        None => return serde_json::to_string(&not_detected).unwrap_or_default(),
+    };
+
+    if !victim_token_is_safe(&victim) {
+        return serde_json::to_string(&not_detected).unwrap_or_default();
+    }
+
+    // Estimating extracted value requires knowing the pool's reserves; without
+    // them we can confirm timing/sender/slippage but not profitability.
+    let (reserve_in, reserve_out) = match (&victim.reserve_in, &victim.reserve_out) {
+        (Some(_), Some(_)) => (parse_amount(&victim.reserve_in), parse_amount(&victim.reserve_out)),
+        _ => return serde_json::to_string(&not_detected).unwrap_or_default(),
+    };
+
+    let victim_in = parse_amount(&victim.amount_in);
+    let victim_out_min = parse_amount(&victim.amount_out_min);
+
+    // The attacker can't usefully frontrun with more than the pool's own
+    // `token_in` reserve, so that's a natural upper bound for the search.
+    let (optimal_frontrun_in, best_profit) =
+        find_optimal_frontrun(reserve_in, victim_in, victim_out_min, reserve_in, reserve_out);
+
+    if best_profit <= 0 {
+        return serde_json::to_string(&not_detected).unwrap_or_default();
     }
+
+    let result = SandwichDetectionResult {
+        detected: true,
+        attacker: Some(fr.sender.clone()),
+        victim_hash: Some(victim.hash.clone()),
+        estimated_profit_wei: Some(best_profit.to_string()),
+        optimal_frontrun_in: Some(optimal_frontrun_in.to_string()),
+    };
+
+    serde_json::to_string(&result).unwrap_or_default()
+}
+
+// Standalone salmonella check, exposed to JavaScript via WASM, so the Node
+// layer can screen a token before even building a transaction cluster around
+// it. `sim_params_json` deserializes into `TokenSafetyCheckParams`; an
+// unparseable payload is treated as unsafe, since we'd otherwise have no basis
+// to clear the token.
+#[wasm_bindgen]
+pub fn is_token_salmonella(token: &str, sim_params_json: &str) -> bool {
+    let params: TokenSafetyCheckParams = match serde_json::from_str(sim_params_json) {
+        Ok(p) => p,
+        Err(e) => {
+            eprintln!("Error deserializing token safety params for {}: {:?}", token, e);
+            return true;
+        }
+    };
+
+    let tolerance_bps = params.tolerance_bps.unwrap_or(DEFAULT_TOLERANCE_BPS);
+    !is_safe(&params, tolerance_bps)
+}
+
+// Builds the directed token graph used for arbitrage detection: one edge per
+// swap-shaped transaction, `token_in -> token_out`, weighted by the effective
+// exchange rate that swap achieved. When pool reserves were supplied we
+// simulate the actual constant-product output; otherwise we fall back to the
+// transaction's own `amount_out_min` as a conservative estimate of the rate.
+fn build_swap_edges(transactions: &[Transaction]) -> Vec<SwapEdge> {
+    transactions.iter()
+        .filter_map(|tx| {
+            let token_in = tx.token_in.clone()?;
+            let token_out = tx.token_out.clone()?;
+            let amount_in = parse_amount(&tx.amount_in);
+            if amount_in == 0 {
+                return None;
+            }
+
+            let amount_out = match (&tx.reserve_in, &tx.reserve_out) {
+                (Some(_), Some(_)) => swap_output(
+                    amount_in,
+                    parse_amount(&tx.reserve_in),
+                    parse_amount(&tx.reserve_out),
+                ),
+                _ => parse_amount(&tx.amount_out_min),
+            };
+            if amount_out == 0 {
+                return None;
+            }
+
+            Some(SwapEdge {
+                token_in,
+                token_out,
+                rate: amount_out as f64 / amount_in as f64,
+                tx_hash: tx.hash.clone(),
+                sender: tx.sender.clone(),
+            })
+        })
+        .collect()
+}
+
+// Result of an arbitrage detection pass, serialized to JSON for the Node.js layer.
+#[derive(Debug, Clone, Serialize)]
+pub struct ArbitrageDetectionResult {
+    pub detected: bool,
+    pub atomic: bool,           // true if every swap in the cycle shares one `sender`
+    pub sender: Option<String>,
+    pub token_path: Vec<String>,
+    pub tx_hashes: Vec<String>,
+    pub estimated_profit_multiplier: Option<f64>, // e.g. 1.015 means a 1.5% return on the cycle
+}
+
+impl ArbitrageDetectionResult {
+    fn not_detected() -> Self {
+        ArbitrageDetectionResult {
+            detected: false,
+            atomic: false,
+            sender: None,
+            token_path: Vec::new(),
+            tx_hashes: Vec::new(),
+            estimated_profit_multiplier: None,
+        }
+    }
+}
+
+// Detects cyclic arbitrage across a cluster of transactions, exposed to
+// JavaScript via WASM. Builds a directed token graph from each swap's
+// effective exchange rate and searches for a negative cycle on `-ln(rate)`
+// edge weights (i.e. a cycle whose combined rate exceeds 1 after fees). A
+// cycle whose edges all share one `sender` is reported as `atomic`. Each
+// transaction contributes a single `token_in -> token_out` edge, so a cycle
+// always spans several transactions; swaps chained inside one transaction
+// are not decoded.
+#[wasm_bindgen]
+pub fn detect_arbitrage(transactions_json: &str) -> String {
+    let not_detected = ArbitrageDetectionResult::not_detected();
+
+    let transactions: Vec<Transaction> = match serde_json::from_str(transactions_json) {
+        Ok(txs) => txs,
+        Err(e) => {
+            eprintln!("Error deserializing transactions: {:?}", e);
+            return serde_json::to_string(&not_detected).unwrap_or_default();
+        }
+    };
+
+    let edges = build_swap_edges(&transactions);
+    let cycle = match find_negative_cycle(&edges) {
+        Some(cycle) if cycle.rate_product > 1.0 => cycle,
+        _ => return serde_json::to_string(&not_detected).unwrap_or_default(),
+    };
+
+    let result = ArbitrageDetectionResult {
+        detected: true,
+        atomic: cycle.sender.is_some(),
+        sender: cycle.sender,
+        token_path: cycle.path,
+        tx_hashes: cycle.tx_hashes,
+        estimated_profit_multiplier: Some(cycle.rate_product),
+    };
+
+    serde_json::to_string(&result).unwrap_or_default()
 }
\ No newline at end of file
@@ -0,0 +1,267 @@
+// mev_engine/src/simulate.rs
+//
+// Models a Uniswap-V2 style constant-product pool so that a candidate
+// frontrun/victim/backrun triple can be checked for actual profitability
+// instead of relying on timing coincidence alone.
+
+/// Computes the output amount of a constant-product swap, including the
+/// standard 0.3% Uniswap-V2 fee (997/1000).
+///
+/// `amount_out = (x * 997 * reserve_out) / (reserve_in * 1000 + x * 997)`
+///
+/// The numerator's `x * 997 * reserve_out` term routinely exceeds `u128::MAX`
+/// for ordinary 18-decimal pools (e.g. `x`, `reserve_out` ~1e24 gives a
+/// numerator ~1e51), so — like Uniswap's own `uint256` math — it's computed as
+/// a 256-bit intermediate via [`mul_wide`]/[`div_wide`] rather than raw `u128`
+/// multiplication, which would silently wrap (release) or panic (debug).
+pub fn swap_output(amount_in: u128, reserve_in: u128, reserve_out: u128) -> u128 {
+    if amount_in == 0 || reserve_in == 0 || reserve_out == 0 {
+        return 0;
+    }
+
+    let amount_in_with_fee = match amount_in.checked_mul(997) {
+        Some(v) => v,
+        None => return 0, // absurdly large input; no representable output
+    };
+    let denominator = match reserve_in
+        .checked_mul(1000)
+        .and_then(|v| v.checked_add(amount_in_with_fee))
+    {
+        Some(v) => v,
+        None => return 0,
+    };
+
+    let (numerator_hi, numerator_lo) = mul_wide(amount_in_with_fee, reserve_out);
+    div_wide(numerator_hi, numerator_lo, denominator)
+}
+
+/// Widening multiply: returns the 256-bit product of `a` and `b` as
+/// `(high, low)` 128-bit limbs, using the standard schoolbook 64-bit-limb
+/// decomposition so that no intermediate step can overflow `u128`.
+fn mul_wide(a: u128, b: u128) -> (u128, u128) {
+    let mask = u64::MAX as u128;
+    let a_lo = a & mask;
+    let a_hi = a >> 64;
+    let b_lo = b & mask;
+    let b_hi = b >> 64;
+
+    let lo_lo = a_lo * b_lo; // < 2^128, safe: both operands < 2^64
+    let lo_hi = a_lo * b_hi; // < 2^128
+    let hi_lo = a_hi * b_lo; // < 2^128
+    let hi_hi = a_hi * b_hi; // < 2^128
+
+    // `lo_hi + hi_lo` can itself exceed u128 by at most one bit; carry that
+    // bit explicitly instead of letting it wrap.
+    let (mid, mid_overflow) = lo_hi.overflowing_add(hi_lo);
+    let mid_lo = mid & mask;
+    let mid_hi = mid >> 64;
+
+    let (low, low_carry) = lo_lo.overflowing_add(mid_lo << 64);
+    let high = hi_hi + mid_hi + if mid_overflow { 1u128 << 64 } else { 0 } + u128::from(low_carry);
+
+    (high, low)
+}
+
+/// Divides the 256-bit value `(high, low)` by `divisor`, returning the
+/// quotient. Callers must ensure the true quotient fits in `u128` (true here:
+/// `swap_output`'s result is always less than `reserve_out`). Implemented as
+/// schoolbook binary long division — 256 fixed iterations, so unlike a
+/// data-dependent loop it can't turn a large numerator into a slow path.
+fn div_wide(high: u128, low: u128, divisor: u128) -> u128 {
+    let mut remainder: u128 = 0;
+    let mut quotient: u128 = 0;
+
+    for i in (0..256).rev() {
+        let bit = if i >= 128 {
+            (high >> (i - 128)) & 1
+        } else {
+            (low >> i) & 1
+        };
+        // `remainder < divisor`, so the shift can carry out at most one bit;
+        // when it does, the true value exceeds `divisor` and the wrapped
+        // subtraction below yields the exact new remainder.
+        let carry = remainder >> 127;
+        remainder = (remainder << 1) | bit;
+        if carry == 1 || remainder >= divisor {
+            remainder = remainder.wrapping_sub(divisor);
+            if i < 128 {
+                quotient |= 1u128 << i;
+            }
+        }
+    }
+
+    quotient
+}
+
+/// Result of simulating a frontrun/victim/backrun triple against a single pool.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SandwichSimResult {
+    /// Amount of `token_out` the frontrun receives from the pool.
+    pub frontrun_out: u128,
+    /// Amount of `token_out` the victim receives after the frontrun shifts the reserves.
+    pub victim_out: u128,
+    /// Whether the victim's trade still clears its `amount_out_min`.
+    pub victim_succeeds: bool,
+    /// Amount of `token_in` the backrun receives from selling `frontrun_out` back.
+    pub backrun_out: u128,
+    /// `backrun_out - frontrun_in`, positive only if the attacker actually profited.
+    pub profit: i128,
+}
+
+/// Simulates a sandwich attack against a constant-product pool.
+///
+/// The frontrun buys `token_out` with `frontrun_in` of `token_in`, shifting the
+/// reserves. The victim then swaps `victim_amount_in` of the same pair at the
+/// shifted reserves; `victim_succeeds` reflects whether that output still meets
+/// `victim_amount_out_min`. Finally the backrun sells everything the frontrun
+/// bought back into `token_in`. The attack is only real if `profit` is positive.
+pub fn simulate_sandwich(
+    frontrun_in: u128,
+    victim_amount_in: u128,
+    victim_amount_out_min: u128,
+    reserve_in: u128,
+    reserve_out: u128,
+) -> SandwichSimResult {
+    // Frontrun: token_in -> token_out
+    let frontrun_out = swap_output(frontrun_in, reserve_in, reserve_out);
+    let reserve_in_after_fr = reserve_in + frontrun_in;
+    let reserve_out_after_fr = reserve_out - frontrun_out;
+
+    // Victim: token_in -> token_out, at the reserves the frontrun left behind.
+    let victim_out = swap_output(victim_amount_in, reserve_in_after_fr, reserve_out_after_fr);
+    let victim_succeeds = victim_out >= victim_amount_out_min;
+    let reserve_in_after_victim = reserve_in_after_fr + victim_amount_in;
+    let reserve_out_after_victim = reserve_out_after_fr - victim_out;
+
+    // Backrun: token_out -> token_in, selling back everything the frontrun bought.
+    let backrun_out = swap_output(frontrun_out, reserve_out_after_victim, reserve_in_after_victim);
+
+    let profit = backrun_out as i128 - frontrun_in as i128;
+
+    SandwichSimResult {
+        frontrun_out,
+        victim_out,
+        victim_succeeds,
+        backrun_out,
+        profit,
+    }
+}
+
+/// Ternary search shrinks `[lo, hi]` by a factor of 2/3 per iteration, so
+/// driving a wei-scale range (`max_frontrun_in` up to ~1e27-1e30, i.e. ~2^100,
+/// for realistic 18-decimal pools) down to a few candidates takes about 100 *
+/// log(2)/log(1.5) ≈ 170 iterations, and a full `u128` range about 128 *
+/// log(2)/log(1.5) ≈ 220, not the ~50 production bots budget for typical (much
+/// smaller) search windows. Capped well above that so the loop always narrows
+/// the window to `hi <= lo + 2` (at width 2 `third` is 0 and the `hi = m2`
+/// branch makes no further progress) before the final scan below, instead of
+/// leaving a post-search window of ~1e15+ candidates for it to iterate over.
+const MAX_TERNARY_ITERATIONS: u32 = 256;
+
+/// Searches `[0, max_frontrun_in]` for the frontrun input that maximizes
+/// `backrun_out(x) - x`, subject to the victim's swap still clearing its
+/// `victim_amount_out_min`. The revenue curve is unimodal in `x` (profit rises
+/// as the frontrun pushes more of the victim's slippage budget, then falls off
+/// a cliff once the victim's trade would revert), so ternary search over the
+/// integer range converges quickly.
+///
+/// Returns `(optimal_frontrun_in, best_profit)`. If no `x` in the range leaves
+/// the victim's swap succeeding, `best_profit` is returned as `i128::MIN` to
+/// signal "no viable attack" to the caller.
+pub fn find_optimal_frontrun(
+    max_frontrun_in: u128,
+    victim_amount_in: u128,
+    victim_amount_out_min: u128,
+    reserve_in: u128,
+    reserve_out: u128,
+) -> (u128, i128) {
+    let profit_at = |x: u128| -> i128 {
+        let result = simulate_sandwich(x, victim_amount_in, victim_amount_out_min, reserve_in, reserve_out);
+        if result.victim_succeeds {
+            result.profit
+        } else {
+            i128::MIN
+        }
+    };
+
+    let mut lo: u128 = 0;
+    let mut hi: u128 = max_frontrun_in;
+
+    for _ in 0..MAX_TERNARY_ITERATIONS {
+        if hi <= lo + 1 {
+            break;
+        }
+        let third = (hi - lo) / 3;
+        let m1 = lo + third;
+        let m2 = hi - third;
+
+        if profit_at(m1) < profit_at(m2) {
+            lo = m1 + 1;
+        } else {
+            hi = m2;
+        }
+    }
+
+    // The loop above always drives `hi - lo` below this bound before it exits
+    // (or exhausts `MAX_TERNARY_ITERATIONS`, which is sized to make that
+    // unreachable for any `u128` range); cap the final scan explicitly anyway
+    // so a window this function can't actually produce still can't turn into
+    // a quadrillion-iteration hang.
+    let scan_hi = hi.min(lo.saturating_add(64));
+
+    let mut best_x = lo;
+    let mut best_profit = profit_at(lo);
+    for x in lo..=scan_hi {
+        let p = profit_at(x);
+        if p > best_profit {
+            best_profit = p;
+            best_x = x;
+        }
+    }
+
+    (best_x, best_profit)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn find_optimal_frontrun_terminates_on_wei_scale_reserves() {
+        // Realistic 18-decimal pool reserves (hundreds of millions of tokens)
+        // and a victim swap sized accordingly. Before this fix, a
+        // `max_frontrun_in` this large left the post-search scan window at
+        // ~1e15+ candidates and the function never returned.
+        let reserve_in: u128 = 500_000_000_000_000_000_000_000; // 500M tokens
+        let reserve_out: u128 = 500_000_000_000_000_000_000_000;
+        let victim_amount_in: u128 = 10_000_000_000_000_000_000; // 10 tokens
+        let victim_amount_out_min: u128 = 1; // generous slippage, easy to clear
+
+        let (optimal_x, profit) = find_optimal_frontrun(
+            reserve_in,
+            victim_amount_in,
+            victim_amount_out_min,
+            reserve_in,
+            reserve_out,
+        );
+
+        assert!(optimal_x <= reserve_in);
+        assert!(profit > i128::MIN);
+    }
+
+    #[test]
+    fn swap_output_does_not_overflow_on_wei_scale_pools() {
+        // amount_in * 997 * reserve_out alone is ~1e51 here, far past
+        // u128::MAX (~3.4e38); this must not panic or wrap to garbage.
+        let amount_in: u128 = 1_000_000_000_000_000_000_000; // 1,000 tokens
+        let reserve_in: u128 = 500_000_000_000_000_000_000_000; // 500M tokens
+        let reserve_out: u128 = 500_000_000_000_000_000_000_000;
+
+        let out = swap_output(amount_in, reserve_in, reserve_out);
+
+        // The constant-product invariant guarantees the output is strictly
+        // less than the pool's reserve of the output token.
+        assert!(out > 0);
+        assert!(out < reserve_out);
+    }
+}
@@ -0,0 +1,51 @@
+// mev_engine/src/token_safety.rs
+//
+// "Salmonella" guard: production sandwich bots simulate a buy-then-sell
+// round trip before committing to an attack, because some tokens (transfer-tax,
+// blacklist/honeypot tokens) behave differently for the attacker than the
+// constant-product math assumes. A token that doesn't round-trip cleanly would
+// make a simulated-profitable sandwich fake in practice.
+
+use serde::{Deserialize, Serialize};
+
+/// Default tolerance for buy/sell round-trip mismatches, in basis points (0.5%).
+/// Covers ordinary rounding/slippage noise without hiding a real transfer tax.
+pub const DEFAULT_TOLERANCE_BPS: u32 = 50;
+
+/// Inputs for a token safety check: the amounts a constant-product simulation
+/// *expects* for a buy and the matching reverse sell, versus the amounts
+/// actually observed (e.g. from decoded `Transfer` logs of a real or forked
+/// execution). A well-behaved ERC-20 token will have `actual` ≈ `expected` on
+/// both legs; a salmonella token won't.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TokenSafetyCheckParams {
+    pub expected_buy_out: String,
+    pub actual_buy_out: String,
+    pub expected_sell_out: String,
+    pub actual_sell_out: String,
+    pub tolerance_bps: Option<u32>,
+}
+
+fn parse_amount(amount: &str) -> u128 {
+    amount.parse::<u128>().unwrap_or(0)
+}
+
+fn within_tolerance(expected: u128, actual: u128, tolerance_bps: u32) -> bool {
+    if expected == 0 {
+        return actual == 0;
+    }
+    let diff = expected.abs_diff(actual);
+    diff.saturating_mul(10_000) <= expected.saturating_mul(tolerance_bps as u128)
+}
+
+/// Returns `true` if both legs of the buy/sell round trip land within
+/// `tolerance_bps` of what the constant-product simulation expected.
+pub fn is_safe(params: &TokenSafetyCheckParams, tolerance_bps: u32) -> bool {
+    let expected_buy_out = parse_amount(&params.expected_buy_out);
+    let actual_buy_out = parse_amount(&params.actual_buy_out);
+    let expected_sell_out = parse_amount(&params.expected_sell_out);
+    let actual_sell_out = parse_amount(&params.actual_sell_out);
+
+    within_tolerance(expected_buy_out, actual_buy_out, tolerance_bps)
+        && within_tolerance(expected_sell_out, actual_sell_out, tolerance_bps)
+}
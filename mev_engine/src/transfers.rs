@@ -0,0 +1,82 @@
+// mev_engine/src/transfers.rs
+//
+// Decodes ERC-20 `Transfer` events out of a transaction's logs, so that
+// sandwich detection can match attacker/victim transactions by the token
+// flows they actually produced instead of by a shared `to` (router) address.
+
+use crate::{Log, Transaction};
+
+/// Event signature hash for `Transfer(address indexed from, address indexed to, uint256 value)`,
+/// i.e. `keccak256("Transfer(address,address,uint256)")`. Every ERC-20 `Transfer`
+/// log has this as `topics[0]`.
+const TRANSFER_EVENT_SIGNATURE: &str =
+    "0xddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef";
+
+/// A single decoded token movement: `amount` of `token` moved from `from` to `to`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Transfer {
+    pub token: String,
+    pub from: String,
+    pub to: String,
+    pub amount: u128,
+}
+
+/// Decodes every standard ERC-20 `Transfer` log attached to a transaction.
+///
+/// Logs that aren't a `Transfer` event (wrong signature or indexed-argument
+/// count) are skipped rather than treated as errors, since a transaction's
+/// logs routinely include other events (e.g. Uniswap `Swap`/`Sync`) alongside
+/// the transfers.
+pub fn parse_transfers(tx: &Transaction) -> Vec<Transfer> {
+    let Some(logs) = &tx.logs else {
+        return Vec::new();
+    };
+
+    logs.iter().filter_map(decode_transfer_log).collect()
+}
+
+fn decode_transfer_log(log: &Log) -> Option<Transfer> {
+    if log.topics.len() != 3 {
+        return None;
+    }
+    if !topics_match(&log.topics[0], TRANSFER_EVENT_SIGNATURE) {
+        return None;
+    }
+
+    let from = address_from_topic(&log.topics[1])?;
+    let to = address_from_topic(&log.topics[2])?;
+    let amount = u128_from_hex(&log.data)?;
+
+    Some(Transfer {
+        token: log.address.clone(),
+        from,
+        to,
+        amount,
+    })
+}
+
+fn topics_match(a: &str, b: &str) -> bool {
+    strip_0x(a).eq_ignore_ascii_case(strip_0x(b))
+}
+
+fn strip_0x(s: &str) -> &str {
+    s.strip_prefix("0x").unwrap_or(s)
+}
+
+/// Indexed `address` topics are left-padded to 32 bytes; the address itself is
+/// the final 20 bytes (40 hex characters).
+fn address_from_topic(topic: &str) -> Option<String> {
+    let hex = strip_0x(topic);
+    if hex.len() < 40 {
+        return None;
+    }
+    Some(format!("0x{}", &hex[hex.len() - 40..]))
+}
+
+fn u128_from_hex(data: &str) -> Option<u128> {
+    let hex = strip_0x(data);
+    if hex.is_empty() {
+        return Some(0);
+    }
+    u128::from_str_radix(hex, 16).ok()
+}